@@ -1,25 +1,99 @@
 //! # Encrypter Utility
 //! This binary provides a command-line utility for encrypting and decrypting files.
-//! It uses the encryption functions from the `palangrotte` library.
+//! It uses the encryption functions from the `palangrotte` library. Besides the default
+//! password mode, `encrypt --recipient <recipient_file>` seals a file to an X25519
+//! public key generated by `keygen`, so whoever runs the encryption never needs the
+//! matching identity (private key); `decrypt --identity <identity_file>` is the
+//! counterpart for an admin who does hold it.
 
-use palangrotte::encryption::{encrypt_file, decrypt_file, EncryptedFile, PBKDF2_SALT_LEN};
-use ring::aead::NONCE_LEN;
-use std::fs;
-use std::io::{Read, Write};
+use palangrotte::encryption::{
+    decrypt_file, decrypt_stream, decrypt_with_identity, derive_key_material,
+    derive_verification_tag, encrypt_file, encrypt_stream, encrypt_to_recipient,
+    generate_identity, to_hex, CipherAlgorithm, EncryptedFile, Identity, KeyFile, Recipient,
+    RecipientEncryptedFile, SALT_LEN, STREAM_SIZE_THRESHOLD,
+};
+use rand::RngCore;
 use std::env;
+use std::fs;
+use std::io::BufWriter;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+fn usage(program: &str) {
+    eprintln!(
+        "Usage: {} encrypt <input_file> <output_file> [chacha20poly1305|aes256gcm] [--recipient <recipient_file>|--key-file <key_file>]",
+        program
+    );
+    eprintln!(
+        "       {} decrypt <input_file> <output_file> [--identity <identity_file>|--key-file <key_file>]",
+        program
+    );
+    eprintln!("       {} init <key_file>", program);
+    eprintln!("       {} keygen <identity_file> <recipient_file>", program);
+}
+
+/// Pulls a `--flag <value>` pair out of `args` wherever it appears, removing both from
+/// `args` in place and returning the value. Used so `--recipient`/`--identity`/`--key-file`
+/// can follow the positional input/output/cipher arguments in any position.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let index = args.iter().position(|a| a == flag)?;
+    args.remove(index);
+    if index < args.len() {
+        Some(args.remove(index))
+    } else {
+        None
+    }
+}
+
+/// Parses the optional cipher name following `encrypt`'s output path, defaulting to
+/// ChaCha20-Poly1305 to match `Settings::default`.
+fn parse_cipher(name: Option<&str>) -> Result<CipherAlgorithm> {
+    match name {
+        None | Some("chacha20poly1305") => Ok(CipherAlgorithm::ChaCha20Poly1305),
+        Some("aes256gcm") => Ok(CipherAlgorithm::Aes256Gcm),
+        Some(other) => Err(format!(
+            "Unknown cipher '{}'. Use 'chacha20poly1305' or 'aes256gcm'.",
+            other
+        )
+        .into()),
+    }
+}
+
+/// Derives the same hex-encoded `folders_key` password the daemon expects, from a `--key-file`
+/// written by `init` and the operator's passphrase. Mirrors `palangrotte.rs`'s
+/// `unlock_key_material`, including rejecting the wrong passphrase via the stored verification
+/// tag, so that a file encrypted this way is guaranteed to be one the daemon can unlock.
+fn folders_password_from_key_file(key_file_path: &str) -> Result<String> {
+    let key_file = KeyFile::from_bytes(&fs::read(key_file_path)?)
+        .map_err(|e| format!("Invalid key file: {}", e))?;
+    let passphrase = rpassword::prompt_password("Enter passphrase: ")?;
+
+    let verification_tag = derive_verification_tag(&passphrase, &key_file.salt)
+        .map_err(|_| "Failed to derive verification tag")?;
+    if verification_tag != key_file.verification_tag {
+        return Err("Incorrect passphrase for this key file.".into());
+    }
+
+    let material = derive_key_material(&passphrase, &key_file.salt)
+        .map_err(|_| "Failed to derive key material")?;
+    Ok(to_hex(&material.folders_key))
+}
 
 /// The main function for the encrypter utility.
 ///
-/// This function parses command-line arguments to determine whether to encrypt or decrypt a file.
-/// It prompts the user for a password and then performs the requested operation.
+/// This function parses command-line arguments to determine whether to encrypt or decrypt
+/// a file, to set up a new passphrase via `init`, or to generate an X25519 keypair via
+/// `keygen`. For `encrypt`/`decrypt` it prompts for a password, unless `--recipient`/
+/// `--identity` was given, in which case it uses recipient-mode encryption instead, or
+/// `--key-file` was given, in which case the password is derived from the passphrase and
+/// the `init`-written key file, matching what the daemon itself unlocks `folders.enc` with.
 ///
 /// # Arguments
 ///
-/// * `<encrypt|decrypt>` - The command to perform.
+/// * `<encrypt|decrypt|init|keygen>` - The command to perform.
 /// * `<input_file>` - The path to the input file.
 /// * `<output_file>` - The path to the output file.
 ///
@@ -28,73 +102,196 @@ type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 /// * `Ok(())` - If the operation was successful.
 /// * `Err(Box<dyn std::error::Error>)` - If there was an error.
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 4 {
-        eprintln!("Usage: {} <encrypt|decrypt> <input_file> <output_file>", args[0]);
+    let mut args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        usage(&args[0]);
+        return Ok(());
+    }
+
+    let program = args[0].clone();
+    let command = args[1].clone();
+
+    if command == "init" {
+        if args.len() != 3 {
+            usage(&program);
+            return Ok(());
+        }
+        let key_file_path = &args[2];
+
+        let passphrase = rpassword::prompt_password("Enter passphrase: ")?;
+        let passphrase_confirm = rpassword::prompt_password("Confirm passphrase: ")?;
+        if passphrase != passphrase_confirm {
+            eprintln!("Passphrases do not match.");
+            return Ok(());
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+
+        let verification_tag = derive_verification_tag(&passphrase, &salt)
+            .map_err(|_| "Failed to derive verification tag")?;
+        let key_file = KeyFile { salt, verification_tag };
+        fs::write(key_file_path, key_file.to_bytes())?;
+        #[cfg(unix)]
+        fs::set_permissions(key_file_path, fs::Permissions::from_mode(0o600))?;
+        println!(
+            "Key file written to: {}. The passphrase itself is never stored; keep it safe.",
+            key_file_path
+        );
+        return Ok(());
+    }
+
+    if command == "keygen" {
+        if args.len() != 4 {
+            usage(&program);
+            return Ok(());
+        }
+        let identity_path = &args[2];
+        let recipient_path = &args[3];
+
+        let identity = generate_identity();
+        let recipient = identity.recipient();
+        fs::write(identity_path, identity.to_bytes())?;
+        #[cfg(unix)]
+        fs::set_permissions(identity_path, fs::Permissions::from_mode(0o600))?;
+        fs::write(recipient_path, recipient.to_bytes())?;
+        println!(
+            "Identity written to: {} (keep this offline). Recipient (public key) written to: {}.",
+            identity_path, recipient_path
+        );
         return Ok(());
     }
 
-    let command = &args[1];
-    let input_path = &args[2];
-    let output_path = &args[3];
+    // Pull the optional --recipient/--identity flags out before the positional args are
+    // counted, so they can be placed anywhere after the command.
+    let recipient_path = take_flag(&mut args, "--recipient");
+    let identity_path = take_flag(&mut args, "--identity");
+    let key_file_path = take_flag(&mut args, "--key-file");
+
+    if args.len() < 4 || args.len() > 5 {
+        usage(&program);
+        return Ok(());
+    }
 
-    if !Path::new(input_path).exists() {
+    let input_path = args[2].clone();
+    let output_path = args[3].clone();
+
+    if !Path::new(&input_path).exists() {
         eprintln!("Error: Input file '{}' does not exist.", input_path);
         return Ok(());
     }
 
     match command.as_str() {
         "encrypt" => {
-            let password = rpassword::prompt_password("Enter password: ")?;
-            let password_confirm = rpassword::prompt_password("Confirm password: ")?;
+            let cipher = parse_cipher(args.get(4).map(String::as_str))?;
 
-            if password != password_confirm {
-                eprintln!("Passwords do not match.");
+            if let Some(recipient_path) = recipient_path {
+                let recipient = Recipient::from_bytes(&fs::read(&recipient_path)?)
+                    .map_err(|e| format!("Invalid recipient file: {}", e))?;
+                let plaintext = fs::read(&input_path)?;
+                match encrypt_to_recipient(&plaintext, &recipient, cipher) {
+                    Ok(enc_data) => {
+                        fs::write(&output_path, enc_data.to_bytes())?;
+                        println!("File encrypted successfully to: {}", output_path);
+                    }
+                    Err(_) => eprintln!("Error during file encryption."),
+                }
                 return Ok(());
             }
 
-            let plaintext = fs::read(input_path)?;
-            match encrypt_file(&plaintext, &password) {
-                Ok(enc_data) => {
-                    let mut file = fs::File::create(output_path)?;
-                    file.write_all(&enc_data.salt)?;
-                    file.write_all(&enc_data.nonce)?;
-                    file.write_all(&enc_data.ciphertext_with_tag)?;
-                    println!("File encrypted successfully to: {}", output_path);
+            let password = if let Some(key_file_path) = key_file_path {
+                folders_password_from_key_file(&key_file_path)?
+            } else {
+                let password = rpassword::prompt_password("Enter password: ")?;
+                let password_confirm = rpassword::prompt_password("Confirm password: ")?;
+
+                if password != password_confirm {
+                    eprintln!("Passwords do not match.");
+                    return Ok(());
                 }
-                Err(_) => {
-                    eprintln!("Error during file encryption.");
+                password
+            };
+
+            let input_size = fs::metadata(&input_path)?.len();
+            if input_size > STREAM_SIZE_THRESHOLD {
+                let input_file = fs::File::open(&input_path)?;
+                let output_file = BufWriter::new(fs::File::create(&output_path)?);
+                match encrypt_stream(input_file, output_file, &password, cipher) {
+                    Ok(()) => println!("File encrypted successfully to: {}", output_path),
+                    Err(e) => eprintln!("Error during file encryption: {}", e),
+                }
+            } else {
+                let plaintext = fs::read(&input_path)?;
+                match encrypt_file(&plaintext, &password, cipher) {
+                    Ok(enc_data) => {
+                        fs::write(&output_path, enc_data.to_bytes())?;
+                        println!("File encrypted successfully to: {}", output_path);
+                    }
+                    Err(_) => {
+                        eprintln!("Error during file encryption.");
+                    }
                 }
             }
         }
         "decrypt" => {
-            let password = rpassword::prompt_password("Enter password: ")?;
-            let mut encrypted_file = fs::File::open(input_path)?;
-            let mut salt = [0u8; PBKDF2_SALT_LEN];
-            encrypted_file.read_exact(&mut salt)?;
-            let mut nonce = [0u8; NONCE_LEN];
-            encrypted_file.read_exact(&mut nonce)?;
-            let mut ciphertext_with_tag = Vec::new();
-            encrypted_file.read_to_end(&mut ciphertext_with_tag)?;
-
-            let read_enc_data = EncryptedFile {
-                salt,
-                nonce,
-                ciphertext_with_tag,
-            };
+            if args.len() != 4 {
+                usage(&program);
+                return Ok(());
+            }
+
+            if let Some(identity_path) = identity_path {
+                let identity = Identity::from_bytes(&fs::read(&identity_path)?)
+                    .map_err(|e| format!("Invalid identity file: {}", e))?;
+                let raw = fs::read(&input_path)?;
+                let read_enc_data = RecipientEncryptedFile::from_bytes(&raw)
+                    .map_err(|e| format!("Invalid recipient-encrypted file: {}", e))?;
+                match decrypt_with_identity(read_enc_data, &identity) {
+                    Ok(decrypted) => {
+                        fs::write(&output_path, decrypted)?;
+                        println!("File decrypted successfully to: {}", output_path);
+                    }
+                    Err(_) => {
+                        eprintln!(
+                            "Error during file decryption. Wrong identity or corrupted data."
+                        );
+                    }
+                }
+                return Ok(());
+            }
 
-            match decrypt_file(read_enc_data, &password) {
-                Ok(decrypted) => {
-                    fs::write(output_path, decrypted)?;
-                    println!("File decrypted successfully to: {}", output_path);
+            let password = if let Some(key_file_path) = key_file_path {
+                folders_password_from_key_file(&key_file_path)?
+            } else {
+                rpassword::prompt_password("Enter password: ")?
+            };
+            let input_size = fs::metadata(&input_path)?.len();
+            if input_size > STREAM_SIZE_THRESHOLD {
+                let input_file = fs::File::open(&input_path)?;
+                let output_file = BufWriter::new(fs::File::create(&output_path)?);
+                match decrypt_stream(input_file, output_file, &password) {
+                    Ok(()) => println!("File decrypted successfully to: {}", output_path),
+                    Err(e) => eprintln!("Error during file decryption: {}", e),
                 }
-                Err(_) => {
-                    eprintln!("Error during file decryption. Incorrect password or corrupted data.");
+            } else {
+                let raw = fs::read(&input_path)?;
+                let read_enc_data = EncryptedFile::from_bytes(&raw)
+                    .map_err(|e| format!("Invalid encrypted file: {}", e))?;
+
+                match decrypt_file(read_enc_data, &password) {
+                    Ok(decrypted) => {
+                        fs::write(&output_path, decrypted)?;
+                        println!("File decrypted successfully to: {}", output_path);
+                    }
+                    Err(_) => {
+                        eprintln!(
+                            "Error during file decryption. Incorrect password or corrupted data."
+                        );
+                    }
                 }
             }
         }
         _ => {
-            eprintln!("Invalid command. Use 'encrypt' or 'decrypt'.");
+            eprintln!("Invalid command. Use 'encrypt', 'decrypt', 'init', or 'keygen'.");
         }
     }
 
@@ -0,0 +1,50 @@
+//! # Palangrotte Control Utility
+//! This binary connects to a running daemon's control socket and issues a single
+//! line-delimited JSON command, printing back the JSON response. It is the operator's
+//! way to query status, pause monitoring during maintenance, or trigger a test alert
+//! without editing files and restarting the daemon.
+
+use palangrotte::settings::load_settings;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn usage(program: &str) {
+    eprintln!(
+        "Usage: {} <status|pause|resume|list-folders|test-alert|reload-settings>",
+        program
+    );
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        usage(&args[0]);
+        return Ok(());
+    }
+
+    let command = match args[1].as_str() {
+        "status" => "{\"command\":\"status\"}",
+        "pause" => "{\"command\":\"pause\"}",
+        "resume" => "{\"command\":\"resume\"}",
+        "list-folders" => "{\"command\":\"list-folders\"}",
+        "test-alert" => "{\"command\":\"test-alert\"}",
+        "reload-settings" => "{\"command\":\"reload-settings\"}",
+        _ => {
+            usage(&args[0]);
+            return Ok(());
+        }
+    };
+
+    let settings = load_settings();
+    let mut stream = UnixStream::connect(&settings.control_socket_path)?;
+    writeln!(stream, "{}", command)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    print!("{}", reply);
+
+    Ok(())
+}
@@ -3,17 +3,50 @@
 //! It initializes the watcher, reads the encrypted folder configuration, and listens for file system events.
 
 use notify::{RecommendedWatcher, Watcher};
-use palangrotte::canary::{handle_event, register_canary_folder};
-use palangrotte::encryption::{decrypt_file, EncryptedFile, PBKDF2_SALT_LEN};
-use palangrotte::logger::log_message;
+use palangrotte::canary::{handle_event, register_canary_folder, spawn_debounce_flusher};
+use palangrotte::encryption::{
+    decrypt_file, derive_key_material, derive_verification_tag, to_hex, EncryptedFile, KeyFile,
+};
+use palangrotte::engine::{spawn_control_server, Engine};
+use palangrotte::logger::{self, log_message, LogConfig};
+use palangrotte::manifest::spawn_manifest_verifier;
+use palangrotte::notify_access::drain_spool;
 use palangrotte::settings::{load_settings, Settings};
-use ring::aead::NONCE_LEN;
 use std::fs;
-use std::io::Read;
 use std::process;
 use std::sync::mpsc::channel;
 use std::sync::Arc;
 
+/// Prompts for the operator's passphrase, re-derives the folders/integrity subkeys from
+/// `settings.key_file`'s salt, and checks them against its verification tag before
+/// returning them. This is the only place the passphrase itself is handled; from here
+/// on the daemon only ever sees the derived, purpose-specific subkeys.
+///
+/// # Returns
+///
+/// * `Ok((folders_key, integrity_key))` - The hex-encoded subkeys, on a matching passphrase.
+/// * `Err(String)` - A human-readable reason the key file couldn't be read or the
+///   passphrase didn't match.
+fn unlock_key_material(settings: &Settings) -> Result<(String, String), String> {
+    let raw = fs::read(&settings.key_file)
+        .map_err(|e| format!("Failed to read key file '{}': {}", settings.key_file, e))?;
+    let key_file = KeyFile::from_bytes(&raw)
+        .map_err(|e| format!("Invalid key file '{}': {}", settings.key_file, e))?;
+
+    let passphrase = rpassword::prompt_password("Enter passphrase: ")
+        .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+
+    let expected_tag = derive_verification_tag(&passphrase, &key_file.salt)
+        .map_err(|_| "Failed to derive verification tag".to_string())?;
+    if expected_tag != key_file.verification_tag {
+        return Err("Incorrect passphrase.".to_string());
+    }
+
+    let material = derive_key_material(&passphrase, &key_file.salt)
+        .map_err(|_| "Failed to derive key material".to_string())?;
+    Ok((to_hex(&material.folders_key), to_hex(&material.integrity_key)))
+}
+
 /// Reads and decrypts the canary folders file.
 ///
 /// # Arguments
@@ -29,19 +62,9 @@ fn read_canary_folders(
     password: &str,
     settings: &Settings,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut encrypted_file = fs::File::open(&settings.folders_file)?;
-    let mut salt = [0u8; PBKDF2_SALT_LEN];
-    encrypted_file.read_exact(&mut salt)?;
-    let mut nonce = [0u8; NONCE_LEN];
-    encrypted_file.read_exact(&mut nonce)?;
-    let mut ciphertext_with_tag = Vec::new();
-    encrypted_file.read_to_end(&mut ciphertext_with_tag)?;
-
-    let read_enc_data = EncryptedFile {
-        salt,
-        nonce,
-        ciphertext_with_tag,
-    };
+    let raw = fs::read(&settings.folders_file)?;
+    let read_enc_data =
+        EncryptedFile::from_bytes(&raw).map_err(|e| format!("Invalid folders file: {}", e))?;
 
     let decrypted_data = decrypt_file(read_enc_data, password)
         .map_err(|_| "Failed to decrypt folders file. Incorrect password or corrupted data.")?;
@@ -56,10 +79,33 @@ fn read_canary_folders(
 /// registers the folders for monitoring, and then enters a loop to handle file system events.
 #[tokio::main]
 async fn main() {
-    let settings = Arc::new(load_settings());
+    let mut settings = load_settings();
     let log_file = settings.log_file.clone();
 
-    let password = &settings.keyword;
+    logger::configure(LogConfig {
+        format: settings.log_format,
+        max_log_size: settings.max_log_size,
+        keep: settings.log_rotate_keep,
+    });
+
+    match unlock_key_material(&settings) {
+        Ok((folders_key, integrity_key)) => {
+            settings.canary_key = folders_key;
+            settings.integrity_key = integrity_key;
+        }
+        Err(e) => {
+            let msg = format!("Failed to unlock key material: {}", e);
+            log_message(&log_file, &msg);
+            eprintln!("{}", msg);
+            process::exit(1);
+        }
+    }
+
+    let settings = Arc::new(settings);
+
+    drain_spool(&settings).await;
+
+    let password = settings.canary_key.clone();
 
     let (tx, rx) = channel();
 
@@ -87,24 +133,24 @@ async fn main() {
         }
     };
 
-    match read_canary_folders(password, &settings) {
+    let mut registered_folders = Vec::new();
+    match read_canary_folders(&password, &settings) {
         Ok(folders) => {
             if folders.is_empty() {
                 let msg = format!("{} is empty.", settings.folders_file);
                 log_message(&log_file, &msg);
             } else {
-                let mut successful_registrations = 0;
                 for folder in &folders {
                     match register_canary_folder(folder, &mut watcher, &settings) {
                         Ok(_) => {
-                            successful_registrations += 1;
+                            registered_folders.push(folder.clone());
                             println!("Registered folder for monitoring: {}", folder);
                         }
                         Err(e) => log_message(&log_file, &e),
                     }
                 }
 
-                if successful_registrations == 0 {
+                if registered_folders.is_empty() {
                     let msg = "No canary folders could be registered. Exiting.";
                     log_message(&log_file, msg);
                     eprintln!("{}", msg);
@@ -120,8 +166,14 @@ async fn main() {
         }
     }
 
+    let engine = Engine::new(Arc::clone(&settings), registered_folders.clone());
+    spawn_control_server(engine.clone(), settings.control_socket_path.clone());
+
+    spawn_debounce_flusher(engine.clone());
+    spawn_manifest_verifier(registered_folders, engine.clone());
+
     // The receiver will block the main thread until a message is received
-    for (event, settings) in rx {
-        handle_event(event, settings).await;
+    for (event, _settings) in rx {
+        handle_event(event, engine.clone()).await;
     }
 }
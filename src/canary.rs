@@ -2,19 +2,23 @@
 //! This module manages canary folder and file operations, including creation, timestamp updates,
 //! and registering folders with the file watcher.
 
-use crate::logger::log_message;
-use crate::notify_access::notify_service;
+use crate::engine::Engine;
+use crate::logger::{log_event, log_message, Severity};
+use crate::notify_access::{build_sinks, notify_sinks};
 use std::sync::Arc;
 use crate::settings::Settings;
 use filetime::{set_file_mtime, FileTime};
+use notify::event::{EventKind, ModifyKind, RemoveKind};
 use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
 use rand::Rng;
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::Path;
-use system_shutdown;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 /// Registers a canary folder for monitoring.
 ///
@@ -144,6 +148,8 @@ fn create_canary_files(folder_path: &str, settings: &Settings) {
     }
     let msg = format!("Created {} canary files in {}.", num_files, folder_path);
     log_message(&settings.log_file, &msg);
+
+    crate::manifest::write_manifest(folder_path, settings);
 }
 
 /// Called when a modification is detected in a monitored folder.
@@ -155,162 +161,315 @@ fn create_canary_files(folder_path: &str, settings: &Settings) {
 async fn modification_detection(foldername: &str, settings: &Settings) {
     println!("Modification detected in folder or file: {}", foldername);
     let msg = format!("Modification detected in folder or file: {}", foldername);
-    log_message(&settings.log_file, &msg);
-    notify_service(&settings.service_url, foldername, &settings.log_file).await;
-    notify_sessions(settings);
-    shutdown_system(settings);
+    log_event(
+        &settings.log_file,
+        Severity::Critical,
+        Some("canary_modification_detected"),
+        &msg,
+    );
+    let sinks = build_sinks(settings);
+    notify_sinks(&sinks, foldername, &settings.log_file).await;
 }
 
-/// Handles a file system event.
-///
-/// This function is called when a file system event is received from the watcher.
-/// It iterates over the paths in the event and spawns a new Tokio task for each path
-/// to call `modification_detection` asynchronously.
+/// Escalates a detection made outside the real-time watcher path, such as the periodic
+/// manifest verification sweep, through the same `modification_detection` handling as a
+/// live `notify` event.
 ///
 /// # Arguments
 ///
-/// * `event` - The file system event.
+/// * `foldername` - The name of the folder or file flagged by the detector.
 /// * `settings` - The application settings.
-pub async fn handle_event(event: Event, settings: Arc<Settings>) {
-    for path in &event.paths {
-        if let Some(folder_str) = path.to_str() {
-            let folder_str_clone = folder_str.to_string();
-            let settings_clone = Arc::clone(&settings);
-            tokio::spawn(async move {
-                modification_detection(&folder_str_clone, &settings_clone).await;
-            });
-        }
+/// * `engine` - The shared engine handle; while paused, the flagged detection is logged
+///   and ignored instead of escalating, matching [`handle_event`]'s behavior.
+pub async fn trigger_detection(foldername: &str, settings: &Settings, engine: &Engine) {
+    if engine.is_paused() {
+        log_message(
+            &settings.log_file,
+            &format!("Monitoring paused; ignoring flagged detection {}.", foldername),
+        );
+        return;
+    }
+    modification_detection(foldername, settings).await;
+}
+
+/// A canary touch that is waiting out the debounce window before it is weighed against
+/// the multi-hit threshold.
+struct PendingHit {
+    kind: EventKind,
+    last_seen: Instant,
+}
+
+/// Tracks in-flight canary touches (debounced) and recently escalated ones (for the
+/// rolling weighted threshold). `recent_hits` is keyed by path, not by event, so a single
+/// file touched repeatedly (e.g. an editor autosaving every few seconds) contributes its
+/// [`event_weight`] once towards the rolling score instead of once per release — the
+/// score is a sum over *distinct* disturbed files, the way the threshold was specified.
+#[derive(Default)]
+struct DebounceState {
+    pending: HashMap<PathBuf, PendingHit>,
+    recent_hits: HashMap<PathBuf, (Instant, u32)>,
+}
+
+static DEBOUNCE_STATE: OnceLock<Mutex<DebounceState>> = OnceLock::new();
+
+fn debounce_state() -> &'static Mutex<DebounceState> {
+    DEBOUNCE_STATE.get_or_init(|| Mutex::new(DebounceState::default()))
+}
+
+/// How much a single touch weighs towards `trigger_threshold`. Deletions and renames
+/// are the signature of a ransomware sweep, so they count for more than a metadata-only
+/// touch such as an editor save.
+fn event_weight(kind: &EventKind) -> u32 {
+    match kind {
+        EventKind::Remove(RemoveKind::File) | EventKind::Modify(ModifyKind::Name(_)) => 2,
+        _ => 1,
     }
 }
 
-/// Notifies logged-in user sessions about a security alert.
+/// Records that `path` was released with `weight`, then drops any `recent_hits` entry
+/// older than `window` relative to `now` and returns the sum of the remaining *distinct*
+/// paths' weights — the rolling score [`spawn_debounce_flusher`] compares against
+/// `trigger_threshold`. A path already in the window keeps its original arrival time
+/// (so it still ages out) but takes the larger of its old and new weight, so repeatedly
+/// touching the same file can't rack up an ever-growing score.
+fn record_hit_and_score(
+    recent_hits: &mut HashMap<PathBuf, (Instant, u32)>,
+    path: PathBuf,
+    weight: u32,
+    now: Instant,
+    window: Duration,
+) -> u32 {
+    recent_hits.retain(|_, (seen, _)| now.duration_since(*seen) <= window);
+    recent_hits
+        .entry(path)
+        .and_modify(|(_, existing_weight)| *existing_weight = (*existing_weight).max(weight))
+        .or_insert((now, weight));
+    recent_hits.values().map(|(_, weight)| *weight).sum()
+}
+
+/// Handles a file system event.
+///
+/// This function is called when a file system event is received from the watcher.
+/// Rather than escalating immediately, it buffers the touched paths keyed by their
+/// arrival instant; a periodic flush (see [`spawn_debounce_flusher`]) collapses
+/// duplicate events within the debounce window and only escalates to
+/// `modification_detection` once enough distinct canary files have been disturbed
+/// within the rolling trigger window — the signature of ransomware sweeping a
+/// directory, as opposed to a single benign touch.
 ///
 /// # Arguments
 ///
-/// * `settings` - The application settings.
-#[cfg(windows)]
-fn notify_sessions(settings: &Settings) {
-    use std::ffi::OsStr;
-    use std::iter::once;
-    use std::os::windows::ffi::OsStrExt;
-    use std::ptr;
-    use windows_sys::Win32::System::RemoteDesktop::{
-        WTS_CURRENT_SERVER_HANDLE, WTS_SESSION_INFOW, WTSActive, WTSEnumerateSessionsW,
-        WTSFreeMemory, WTSSendMessageW,
-    };
-    use windows_sys::Win32::UI::WindowsAndMessaging::MB_OK;
-
-    let title: Vec<u16> = OsStr::new(&settings.notification_title)
-        .encode_wide()
-        .chain(once(0))
-        .collect();
-    let message: Vec<u16> = OsStr::new(&settings.notification_message)
-        .encode_wide()
-        .chain(once(0))
-        .collect();
-
-    let mut session_info_ptr: *mut WTS_SESSION_INFOW = ptr::null_mut();
-    let mut count = 0;
-
-    unsafe {
-        if WTSEnumerateSessionsW(
-            WTS_CURRENT_SERVER_HANDLE,
-            0,
-            1,
-            &mut session_info_ptr,
-            &mut count,
-        ) != 0
-        {
-            let session_info = std::slice::from_raw_parts(session_info_ptr, count as usize);
-            for session in session_info {
-                if session.State == WTSActive {
-                    let mut response = 0;
-                    WTSSendMessageW(
-                        WTS_CURRENT_SERVER_HANDLE,
-                        session.SessionId,
-                        title.as_ptr() as *mut _,
-                        (title.len() - 1) as u32 * 2,
-                        message.as_ptr() as *mut _,
-                        (message.len() - 1) as u32 * 2,
-                        MB_OK,
-                        30, // timeout 30 seconds
-                        &mut response,
-                        1, // wait for response
-                    );
-                }
-            }
-            WTSFreeMemory(session_info_ptr as *mut _);
-            log_message(&settings.log_file, "Successfully notified user sessions.");
-        } else {
-            log_message(&settings.log_file, "Failed to enumerate user sessions.");
-        }
+/// * `event` - The file system event.
+/// * `engine` - The shared engine handle; while paused (via the `pause` control
+///   command), the event is logged and ignored instead of being buffered for escalation.
+///   Settings are read fresh from `engine` rather than a snapshot, so a `reload-settings`
+///   control command changes the log file this logs to without a restart.
+pub async fn handle_event(event: Event, engine: Engine) {
+    let settings = engine.settings();
+    if engine.is_paused() {
+        log_message(
+            &settings.log_file,
+            &format!("Monitoring paused; ignoring event {:?}.", event.kind),
+        );
+        return;
     }
-}
-
-#[cfg(unix)]
-fn notify_sessions(settings: &Settings) {
-    use crate::linux_notification::NOTIFY_SCRIPT;
-    use std::process::Command;
-
-    let status = Command::new("sh")
-        .arg("-c")
-        .arg(NOTIFY_SCRIPT)
-        .arg("notify-send-all") // This is $0 for the script
-        .arg(&settings.notification_title)
-        .arg(&settings.notification_message)
-        .status();
-
-    match status {
-        Ok(status) => {
-            if status.success() {
-                log_message(&settings.log_file, "Successfully notified user sessions.");
-            } else {
-                let msg = format!(
-                    "Failed to notify user sessions. Exit code: {}",
-                    status
-                );
-                log_message(&settings.log_file, &msg);
-            }
-        }
-        Err(e) => {
-            let msg = format!("Error executing embedded notify script: {}", e);
-            log_message(&settings.log_file, &msg);
+    if let Ok(mut state) = debounce_state().lock() {
+        for path in &event.paths {
+            state.pending.insert(
+                path.clone(),
+                PendingHit {
+                    kind: event.kind.clone(),
+                    last_seen: Instant::now(),
+                },
+            );
         }
     }
+    log_message(
+        &settings.log_file,
+        &format!("Buffered event {:?} for debounced evaluation.", event.kind),
+    );
 }
 
-/// Shuts down the system.
+/// Spawns the periodic Tokio task that flushes debounced events: it releases any path
+/// that has been quiet for at least `debounce_window`, adds its [`event_weight`] to the
+/// rolling score kept over `trigger_window`, and escalates to `modification_detection`
+/// once that weighted score reaches `trigger_threshold` — so a handful of deletions
+/// reaches the threshold faster than the same number of metadata-only touches.
+///
+/// Reads `trigger_window`/`trigger_threshold`/alert sinks/log file fresh from `engine`
+/// on every tick, so a `reload-settings` control command takes effect on the next flush
+/// without a restart. `debounce_window` itself (the flush cadence) is read once at spawn
+/// time, since a running [`tokio::time::interval`] can't be reconfigured in place.
 ///
 /// # Arguments
 ///
-/// * `settings` - The application settings.
-fn shutdown_system(settings: &Settings) {
-    log_message(&settings.log_file, "Attempting to force system shutdown...");
-    match system_shutdown::force_shutdown() {
-        Ok(_) => {
-            log_message(
-                &settings.log_file,
-                "Forced system shutdown command executed successfully.",
-            );
-        }
-        Err(error) => {
-            let msg = format!(
-                "Forced shutdown failed: {}. Attempting graceful shutdown...",
-                error
-            );
-            log_message(&settings.log_file, &msg);
-            match system_shutdown::shutdown() {
-                Ok(_) => {
-                    log_message(
+/// * `engine` - The shared engine handle settings are re-read from on every tick.
+pub fn spawn_debounce_flusher(engine: Engine) -> tokio::task::JoinHandle<()> {
+    let debounce_window = Duration::from_millis(engine.settings().debounce_window_ms);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(debounce_window.max(Duration::from_millis(50)));
+        loop {
+            ticker.tick().await;
+            let settings = engine.settings();
+            let trigger_window = Duration::from_secs(settings.trigger_window_secs);
+            let trigger_threshold = settings.trigger_threshold;
+            let ready: Vec<(PathBuf, EventKind)> = {
+                let mut state = match debounce_state().lock() {
+                    Ok(state) => state,
+                    Err(_) => continue,
+                };
+                let now = Instant::now();
+                let ready_paths: Vec<PathBuf> = state
+                    .pending
+                    .iter()
+                    .filter(|(_, hit)| now.duration_since(hit.last_seen) >= debounce_window)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+                ready_paths
+                    .into_iter()
+                    .filter_map(|path| state.pending.remove(&path).map(|hit| (path, hit.kind)))
+                    .collect()
+            };
+
+            for (path, kind) in ready {
+                let weight = event_weight(&kind);
+                let triggered = {
+                    let mut state = match debounce_state().lock() {
+                        Ok(state) => state,
+                        Err(_) => continue,
+                    };
+                    let now = Instant::now();
+                    let score = record_hit_and_score(
+                        &mut state.recent_hits,
+                        path.clone(),
+                        weight,
+                        now,
+                        trigger_window,
+                    );
+                    if score >= trigger_threshold {
+                        state.recent_hits.clear();
+                        true
+                    } else {
+                        false
+                    }
+                };
+
+                let path_str = path.to_string_lossy().to_string();
+                if triggered {
+                    let msg = format!(
+                        "Trigger threshold reached (weighted score of at least {} within {:?}); escalating.",
+                        trigger_threshold, trigger_window
+                    );
+                    log_event(
                         &settings.log_file,
-                        "Graceful system shutdown command executed successfully.",
+                        Severity::Warning,
+                        Some("canary_trigger_threshold_reached"),
+                        &msg,
+                    );
+                    let settings_clone = Arc::clone(&settings);
+                    tokio::spawn(async move {
+                        modification_detection(&path_str, &settings_clone).await;
+                    });
+                } else {
+                    let msg = format!(
+                        "Canary touch debounced: {} ({:?}, weight {})",
+                        path_str, kind, weight
                     );
-                }
-                Err(error) => {
-                    let msg = format!("Graceful shutdown also failed: {}", error);
                     log_message(&settings.log_file, &msg);
                 }
             }
         }
+    })
+}
+
+// Local desktop notification and forced-shutdown reactions now live as `AlertSink`
+// implementations in `notify_access` (`LocalNotificationSink`, `ShutdownSink`), selected
+// like any other backend via `settings.alert_sinks` instead of being fixed calls here.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_weight_counts_deletion_and_rename_as_double() {
+        assert_eq!(event_weight(&EventKind::Remove(RemoveKind::File)), 2);
+        assert_eq!(
+            event_weight(&EventKind::Modify(ModifyKind::Name(
+                notify::event::RenameMode::Any
+            ))),
+            2
+        );
+    }
+
+    #[test]
+    fn event_weight_counts_other_kinds_as_one() {
+        assert_eq!(event_weight(&EventKind::Remove(RemoveKind::Other)), 1);
+        assert_eq!(
+            event_weight(&EventKind::Modify(ModifyKind::Data(
+                notify::event::DataChange::Any
+            ))),
+            1
+        );
+    }
+
+    #[test]
+    fn record_hit_and_score_drops_stale_entries_and_sums_the_rest() {
+        let mut hits = HashMap::new();
+        let window = Duration::from_secs(5);
+        let base = Instant::now();
+        hits.insert(PathBuf::from("stale"), (base, 2));
+        hits.insert(PathBuf::from("fresh_a"), (base + Duration::from_secs(6), 2));
+
+        let now = base + Duration::from_secs(6);
+        let score = record_hit_and_score(&mut hits, PathBuf::from("fresh_b"), 1, now, window);
+
+        assert_eq!(score, 3);
+        assert_eq!(hits.len(), 2);
+        assert!(!hits.contains_key(Path::new("stale")));
+    }
+
+    #[test]
+    fn record_hit_and_score_reaches_threshold_faster_with_heavier_events() {
+        let window = Duration::from_secs(5);
+        let base = Instant::now();
+
+        let mut weighted = HashMap::new();
+        record_hit_and_score(&mut weighted, PathBuf::from("a"), 2, base, window);
+        let weighted_score =
+            record_hit_and_score(&mut weighted, PathBuf::from("b"), 2, base, window);
+        assert!(weighted_score >= 3);
+
+        let mut unweighted = HashMap::new();
+        record_hit_and_score(&mut unweighted, PathBuf::from("a"), 1, base, window);
+        let unweighted_score =
+            record_hit_and_score(&mut unweighted, PathBuf::from("b"), 1, base, window);
+        assert!(unweighted_score < 3);
+    }
+
+    #[test]
+    fn record_hit_and_score_counts_a_repeatedly_touched_path_only_once() {
+        let window = Duration::from_secs(5);
+        let base = Instant::now();
+        let mut hits = HashMap::new();
+
+        record_hit_and_score(&mut hits, PathBuf::from("passwords.txt"), 1, base, window);
+        record_hit_and_score(
+            &mut hits,
+            PathBuf::from("passwords.txt"),
+            1,
+            base + Duration::from_secs(1),
+            window,
+        );
+        let score = record_hit_and_score(
+            &mut hits,
+            PathBuf::from("passwords.txt"),
+            1,
+            base + Duration::from_secs(2),
+            window,
+        );
+
+        assert_eq!(score, 1);
+        assert_eq!(hits.len(), 1);
     }
 }
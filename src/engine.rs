@@ -0,0 +1,198 @@
+//! # Engine Module
+//! Hoists the watcher registration and detection state behind a shared, clonable
+//! `Engine` handle, and exposes it over a local control socket so an operator can query
+//! status, pause monitoring for legitimate maintenance, or trigger a test alert without
+//! editing files and restarting the daemon. The companion `palangrotte-ctl` binary
+//! speaks the same line-delimited JSON protocol from the other end.
+
+use crate::logger::log_message;
+use crate::notify_access::{build_sinks, notify_sinks};
+use crate::settings::{load_settings, Settings};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixListener;
+
+struct EngineState {
+    settings: Arc<Settings>,
+    folders: Vec<String>,
+    paused: bool,
+}
+
+/// A shared handle onto the running daemon's detection state. Cloning an `Engine` is
+/// cheap; every clone sees the same underlying state.
+#[derive(Clone)]
+pub struct Engine {
+    state: Arc<Mutex<EngineState>>,
+}
+
+impl Engine {
+    pub fn new(settings: Arc<Settings>, folders: Vec<String>) -> Self {
+        Engine {
+            state: Arc::new(Mutex::new(EngineState {
+                settings,
+                folders,
+                paused: false,
+            })),
+        }
+    }
+
+    /// The currently active settings. Changes after a `reload-settings` command.
+    pub fn settings(&self) -> Arc<Settings> {
+        self.state.lock().unwrap().settings.clone()
+    }
+
+    /// Whether monitoring is currently paused; `handle_event` log-and-ignores while true.
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn pause(&self) {
+        self.state.lock().unwrap().paused = true;
+    }
+
+    pub fn resume(&self) {
+        self.state.lock().unwrap().paused = false;
+    }
+
+    pub fn folders(&self) -> Vec<String> {
+        self.state.lock().unwrap().folders.clone()
+    }
+
+    /// Re-reads `palangrotte.toml` and swaps it in as the active settings. The
+    /// passphrase-derived subkeys aren't in the file (see `Settings::canary_key`), so
+    /// they're carried forward from the settings being replaced rather than re-prompted.
+    ///
+    /// `handle_event`, `spawn_debounce_flusher`, and `spawn_manifest_verifier` all read
+    /// settings back out of this `Engine` on every event/tick rather than holding their
+    /// own snapshot, so this takes effect on the real detection path too — not just
+    /// control-server-issued actions like `test-alert`. The exceptions are each task's
+    /// own polling cadence (`debounce_window_ms`, `manifest_check_interval_secs`), which
+    /// is read once at startup since a running `tokio::time::interval` can't be
+    /// reconfigured in place, and the log format and rotation policy, which
+    /// `logger::configure` fixes once at startup; changing those still needs a restart.
+    fn reload_settings(&self) -> Arc<Settings> {
+        let mut fresh = load_settings();
+        {
+            let current = &self.state.lock().unwrap().settings;
+            fresh.canary_key.clone_from(&current.canary_key);
+            fresh.integrity_key.clone_from(&current.integrity_key);
+        }
+        let fresh = Arc::new(fresh);
+        self.state.lock().unwrap().settings = Arc::clone(&fresh);
+        fresh
+    }
+}
+
+/// A control command sent to the daemon as one line of JSON.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum Command {
+    Status,
+    Pause,
+    Resume,
+    ListFolders,
+    TestAlert,
+    ReloadSettings,
+}
+
+/// The daemon's reply to a control command, also one line of JSON.
+#[derive(Debug, Serialize)]
+struct Response {
+    ok: bool,
+    data: serde_json::Value,
+}
+
+async fn handle_command(engine: &Engine, command: Command) -> Response {
+    match command {
+        Command::Status => Response {
+            ok: true,
+            data: serde_json::json!({
+                "paused": engine.is_paused(),
+                "folder_count": engine.folders().len(),
+            }),
+        },
+        Command::Pause => {
+            engine.pause();
+            Response {
+                ok: true,
+                data: serde_json::json!({ "paused": true }),
+            }
+        }
+        Command::Resume => {
+            engine.resume();
+            Response {
+                ok: true,
+                data: serde_json::json!({ "paused": false }),
+            }
+        }
+        Command::ListFolders => Response {
+            ok: true,
+            data: serde_json::json!({ "folders": engine.folders() }),
+        },
+        Command::TestAlert => {
+            let settings = engine.settings();
+            let sinks = build_sinks(&settings);
+            notify_sinks(&sinks, "test-alert", &settings.log_file).await;
+            Response {
+                ok: true,
+                data: serde_json::json!({ "sinks_notified": sinks.len() }),
+            }
+        }
+        Command::ReloadSettings => {
+            let settings = engine.reload_settings();
+            Response {
+                ok: true,
+                data: serde_json::json!({ "folders_file": settings.folders_file }),
+            }
+        }
+    }
+}
+
+/// Spawns the Unix-domain control listener: each connection is read line by line, each
+/// line parsed as a [`Command`] and answered with one JSON [`Response`] line.
+///
+/// # Arguments
+///
+/// * `engine` - The shared engine handle commands are dispatched against.
+/// * `socket_path` - Path of the Unix socket to listen on; removed and recreated on bind.
+pub fn spawn_control_server(engine: Engine, socket_path: String) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = match UnixListener::bind(&socket_path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                let msg = format!("Failed to bind control socket {}: {}", socket_path, e);
+                log_message(&engine.settings().log_file, &msg);
+                return;
+            }
+        };
+
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(_) => continue,
+            };
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let response = match serde_json::from_str::<Command>(&line) {
+                        Ok(command) => handle_command(&engine, command).await,
+                        Err(e) => Response {
+                            ok: false,
+                            data: serde_json::json!({ "error": e.to_string() }),
+                        },
+                    };
+                    if let Ok(mut out) = serde_json::to_vec(&response) {
+                        out.push(b'\n');
+                        if writer.write_all(&out).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    })
+}
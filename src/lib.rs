@@ -3,7 +3,9 @@
 //! It includes modules for handling canary files, logging, settings, encryption, and notifications.
 
 pub mod canary;
+pub mod engine;
 pub mod logger;
+pub mod manifest;
 pub mod settings;
 pub mod encryption;
 pub mod notify_access;
@@ -0,0 +1,27 @@
+//! # Linux Desktop Notification Script
+//! Holds the embedded shell script `LocalNotificationSink` shells out to on Unix, since
+//! there's no single API call equivalent to Windows' `WTSSendMessageW` broadcast: each
+//! logged-in graphical session has its own D-Bus session bus, so `notify-send` has to be
+//! re-run once per session with that session's user and bus address.
+
+/// Broadcasts `notify-send "$1" "$2"` to every logged-in graphical session found via
+/// `loginctl`, running it as that session's user with its `DBUS_SESSION_BUS_ADDRESS` so
+/// the notification reaches the right desktop instead of just the daemon's own session.
+pub const NOTIFY_SCRIPT: &str = r#"
+set -eu
+title="$1"
+message="$2"
+
+loginctl list-sessions --no-legend 2>/dev/null | while read -r session_id user_id user_name _rest; do
+    seat=$(loginctl show-session "$session_id" -p Type --value 2>/dev/null || true)
+    case "$seat" in
+        x11|wayland) ;;
+        *) continue ;;
+    esac
+
+    bus_address="unix:path=/run/user/$user_id/bus"
+    su - "$user_name" -c \
+        "DBUS_SESSION_BUS_ADDRESS='$bus_address' DISPLAY=':0' notify-send '$title' '$message'" \
+        >/dev/null 2>&1 || true
+done
+"#;
@@ -1,26 +1,231 @@
 //! # Logger Module
 //! This module provides a simple logging function to write messages to the log file.
+//! Messages can be emitted as free-form text or as one JSON object per line for SIEM
+//! ingestion, and the active log file is rotated once it grows past a configured size.
 
 use chrono::Local;
-use std::fs::OpenOptions;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::process;
+use std::sync::{Mutex, OnceLock};
 
-/// Logs a message to the specified log file.
+/// Severity of a structured log entry. Plain text mode leaves it out of the line (only
+/// the timestamp and message are written), but JSON mode surfaces it as its own field so
+/// a SIEM can filter or alert on it directly instead of pattern-matching free text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Routine, expected activity (folder registered, manifest written, ...).
+    Info,
+    /// Worth a human's attention but not itself an intrusion (manifest flagged a change).
+    Warning,
+    /// A canary was disturbed and the response chain (notification, shutdown, ...) ran.
+    Critical,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// Output format for log entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Free-form `[timestamp] message` lines.
+    Text,
+    /// One JSON object per line, suitable for SIEM ingestion.
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::Text
+    }
+}
+
+/// Global logging behaviour: output format and size-based rotation policy.
+#[derive(Debug, Clone, Copy)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Maximum size in bytes a log file may reach before it is rotated. `0` disables rotation.
+    pub max_log_size: u64,
+    /// Number of rotated generations (`<file>.1`, `<file>.2`, ...) to keep.
+    pub keep: u32,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            format: LogFormat::Text,
+            max_log_size: 0,
+            keep: 0,
+        }
+    }
+}
+
+static LOG_CONFIG: OnceLock<LogConfig> = OnceLock::new();
+
+/// Sets the global logging format and rotation policy. Should be called once at startup,
+/// before the first call to [`log_message`]; subsequent calls have no effect.
+pub fn configure(config: LogConfig) {
+    let _ = LOG_CONFIG.set(config);
+}
+
+fn config() -> LogConfig {
+    LOG_CONFIG.get().copied().unwrap_or_default()
+}
+
+#[derive(Serialize)]
+struct JsonLogEntry<'a> {
+    ts: String,
+    severity: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<&'a str>,
+    message: &'a str,
+    host: &'a str,
+    pid: u32,
+}
+
+/// A single open log file, tracking its own size so rotation doesn't require a `stat`
+/// call per write.
+struct Logger {
+    file: File,
+    size: u64,
+}
+
+impl Logger {
+    fn open(log_file: &str) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(true)
+            .open(log_file)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Logger { file, size })
+    }
+
+    fn write_line(
+        &mut self,
+        log_file: &str,
+        line: &str,
+        max_log_size: u64,
+        keep: u32,
+    ) -> io::Result<()> {
+        let incoming = line.len() as u64 + 1;
+        if max_log_size > 0 && self.size + incoming > max_log_size {
+            self.rotate(log_file, keep)?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += incoming;
+        Ok(())
+    }
+
+    /// Renames the active file to `.1`, shifts older generations up, drops anything
+    /// beyond `keep`, and reopens a fresh file in its place.
+    fn rotate(&mut self, log_file: &str, keep: u32) -> io::Result<()> {
+        if keep > 0 {
+            let oldest = format!("{}.{}", log_file, keep);
+            let _ = fs::remove_file(&oldest);
+            for generation in (1..keep).rev() {
+                let from = format!("{}.{}", log_file, generation);
+                let to = format!("{}.{}", log_file, generation + 1);
+                let _ = fs::rename(&from, &to);
+            }
+            let _ = fs::rename(log_file, format!("{}.1", log_file));
+        } else {
+            let _ = fs::remove_file(log_file);
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(true)
+            .open(log_file)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+static LOGGERS: OnceLock<Mutex<HashMap<String, Logger>>> = OnceLock::new();
+
+fn loggers() -> &'static Mutex<HashMap<String, Logger>> {
+    LOGGERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn hostname() -> String {
+    hostname::get()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Logs a routine, informational message to the specified log file: equivalent to
+/// [`log_event`] with [`Severity::Info`] and no event name, for the many call sites that
+/// have nothing more structured to report than a line of text.
 ///
 /// # Arguments
 ///
 /// * `log_file` - The path to the log file.
 /// * `message` - The message to log.
 pub fn log_message(log_file: &str, message: &str) {
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open(log_file)
-    {
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
-        if let Err(e) = writeln!(file, "[{}] {}", timestamp, message) {
-            eprintln!("Couldn't write to log file: {}", e);
+    log_event(log_file, Severity::Info, None, message);
+}
+
+/// Logs a message to the specified log file, honoring the globally configured format
+/// and rotation policy (see [`configure`]). In JSON mode, `severity` and `event` are
+/// emitted as discrete fields, so a SIEM can filter or alert on them without parsing
+/// free text; in text mode they are not written, only the timestamp and `message`.
+///
+/// # Arguments
+///
+/// * `log_file` - The path to the log file.
+/// * `severity` - How serious this entry is.
+/// * `event` - A short, stable machine-readable name for what happened (e.g.
+///   `"canary_modification_detected"`), or `None` for a plain informational line.
+/// * `message` - The human-readable message to log.
+pub fn log_event(log_file: &str, severity: Severity, event: Option<&str>, message: &str) {
+    let cfg = config();
+    let line = match cfg.format {
+        LogFormat::Text => format!(
+            "[{}] {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            message
+        ),
+        LogFormat::Json => {
+            let entry = JsonLogEntry {
+                ts: Local::now().to_rfc3339(),
+                severity: severity.as_str(),
+                event,
+                message,
+                host: &hostname(),
+                pid: process::id(),
+            };
+            serde_json::to_string(&entry).unwrap_or_else(|_| message.to_string())
+        }
+    };
+
+    if let Ok(mut registry) = loggers().lock() {
+        if !registry.contains_key(log_file) {
+            match Logger::open(log_file) {
+                Ok(logger) => {
+                    registry.insert(log_file.to_string(), logger);
+                }
+                Err(e) => {
+                    eprintln!("Couldn't open log file {}: {}", log_file, e);
+                    return;
+                }
+            }
+        }
+        if let Some(logger) = registry.get_mut(log_file) {
+            if let Err(e) = logger.write_line(log_file, &line, cfg.max_log_size, cfg.keep) {
+                eprintln!("Couldn't write to log file: {}", e);
+            }
         }
     }
 }
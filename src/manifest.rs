@@ -0,0 +1,268 @@
+//! # Manifest Module
+//! Records a tamper-evident manifest of each canary file's size and keyed hash at
+//! creation time, encrypted via the `encryption` module so it can't be trivially
+//! forged, and re-checks it on a periodic sweep. This complements the real-time
+//! `notify` watcher: an attacker who deletes a canary, or who overwrites one and
+//! restores a plausible mtime, is still caught on the next verification pass.
+
+use crate::canary::trigger_detection;
+use crate::encryption::{decrypt_file, encrypt_file, EncryptedFile};
+use crate::engine::Engine;
+use crate::logger::{log_event, log_message, Severity};
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::process;
+use std::time::Duration;
+
+/// Name of the hidden, encrypted manifest file written inside each canary folder.
+const MANIFEST_FILE_NAME: &str = ".plgrt_manifest.enc";
+
+/// A single canary's baseline: its relative path, exact size, and keyed hash.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CanaryEntry {
+    relative_path: String,
+    size: u64,
+    hash: String,
+}
+
+/// The full baseline for a canary folder.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct CanaryManifest {
+    entries: Vec<CanaryEntry>,
+}
+
+fn manifest_path(folder_path: &str) -> PathBuf {
+    Path::new(folder_path).join(MANIFEST_FILE_NAME)
+}
+
+/// A keyed hash of `data`, derived from the passphrase-derived integrity subkey so the
+/// manifest can't be forged without knowing the same secret the daemon re-derives at
+/// startup.
+fn keyed_hash(integrity_key: &str, data: &[u8]) -> String {
+    let key = blake3::hash(integrity_key.as_bytes());
+    blake3::keyed_hash(key.as_bytes(), data).to_hex().to_string()
+}
+
+/// Builds a manifest from the files currently in `folder_path` and writes it, encrypted
+/// with `settings.integrity_key`, to a hidden file inside that folder.
+///
+/// # Arguments
+///
+/// * `folder_path` - The canary folder to snapshot.
+/// * `settings` - The application settings.
+pub fn write_manifest(folder_path: &str, settings: &Settings) {
+    let dir = match fs::read_dir(folder_path) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log_message(
+                &settings.log_file,
+                &format!("Failed to read {} to build manifest: {}", folder_path, e),
+            );
+            return;
+        }
+    };
+
+    let mut entries = Vec::new();
+    for entry in dir.flatten() {
+        let path = entry.path();
+        if !path.is_file() || path.file_name().map(|n| n == MANIFEST_FILE_NAME).unwrap_or(false) {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Ok(data) = fs::read(&path) else {
+            continue;
+        };
+        entries.push(CanaryEntry {
+            relative_path: name.to_string(),
+            size: data.len() as u64,
+            hash: keyed_hash(&settings.integrity_key, &data),
+        });
+    }
+
+    let manifest = CanaryManifest { entries };
+    let Ok(json) = serde_json::to_vec(&manifest) else {
+        return;
+    };
+    match encrypt_file(&json, &settings.integrity_key, settings.cipher) {
+        Ok(enc) => {
+            if let Err(e) = fs::write(manifest_path(folder_path), enc.to_bytes()) {
+                log_message(
+                    &settings.log_file,
+                    &format!("Failed to write manifest for {}: {}", folder_path, e),
+                );
+            }
+        }
+        Err(_) => log_message(
+            &settings.log_file,
+            &format!("Failed to encrypt manifest for {}", folder_path),
+        ),
+    }
+}
+
+fn read_manifest(folder_path: &str, settings: &Settings) -> Option<CanaryManifest> {
+    let raw = fs::read(manifest_path(folder_path)).ok()?;
+    let encrypted = EncryptedFile::from_bytes(&raw).ok()?;
+    let json = decrypt_file(encrypted, &settings.integrity_key).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// Re-reads every canary recorded in `folder_path`'s manifest, recomputing its hash,
+/// and returns the relative paths found missing, resized, or content-changed.
+fn verify_folder(folder_path: &str, settings: &Settings) -> Vec<String> {
+    let Some(manifest) = read_manifest(folder_path, settings) else {
+        return Vec::new();
+    };
+
+    let mut flagged = Vec::new();
+    for entry in &manifest.entries {
+        let path = Path::new(folder_path).join(&entry.relative_path);
+        match fs::read(&path) {
+            Ok(data) => {
+                if data.len() as u64 != entry.size
+                    || keyed_hash(&settings.integrity_key, &data) != entry.hash
+                {
+                    flagged.push(entry.relative_path.clone());
+                }
+            }
+            Err(_) => flagged.push(entry.relative_path.clone()),
+        }
+    }
+    flagged
+}
+
+/// Spawns the periodic verification sweep: every `settings.manifest_check_interval_secs`,
+/// each registered folder's canaries are re-hashed against their manifest, and any
+/// missing/resized/changed file feeds the same escalation path as a real-time event.
+///
+/// Settings are re-read from `engine` on every sweep rather than captured once at spawn
+/// time, so `reload-settings` changes the log file/alert sinks the very next sweep uses
+/// (the integrity key is carried over unchanged by a reload). `manifest_check_interval_secs`
+/// (the sweep's own cadence) is read once at
+/// spawn time, since a running [`tokio::time::interval`] can't be reconfigured in place.
+///
+/// # Arguments
+///
+/// * `folders` - The canary folders to sweep.
+/// * `engine` - The shared engine handle settings are re-read from, and which is
+///   consulted before escalating so a `pause` command also holds off the scheduled
+///   sweep, not just the real-time watcher.
+pub fn spawn_manifest_verifier(folders: Vec<String>, engine: Engine) -> tokio::task::JoinHandle<()> {
+    let interval = Duration::from_secs(engine.settings().manifest_check_interval_secs.max(1));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let settings = engine.settings();
+            for folder in &folders {
+                let flagged = verify_folder(folder, &settings);
+                for relative_path in flagged {
+                    let msg = format!(
+                        "Manifest verification flagged {}/{} as missing, resized, or changed.",
+                        folder, relative_path
+                    );
+                    log_event(
+                        &settings.log_file,
+                        Severity::Warning,
+                        Some("manifest_verification_flagged"),
+                        &msg,
+                    );
+                    let foldername = format!("{}/{}", folder, relative_path);
+                    trigger_detection(&foldername, &settings, &engine).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempFolder {
+        path: PathBuf,
+    }
+
+    impl TempFolder {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "plgrt_manifest_test_{}_{}",
+                name,
+                process_unique_suffix()
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempFolder { path }
+        }
+
+        fn path_str(&self) -> &str {
+            self.path.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// A cheap per-test uniquifier so concurrently running tests don't collide on the
+    /// same temp directory; not a cryptographic identifier.
+    fn process_unique_suffix() -> String {
+        format!("{:?}_{}", std::thread::current().id(), process::id())
+    }
+
+    fn test_settings() -> Settings {
+        Settings {
+            integrity_key: "test-integrity-key".to_string(),
+            cipher: crate::encryption::CipherAlgorithm::ChaCha20Poly1305,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn keyed_hash_is_deterministic_and_key_dependent() {
+        let data = b"some canary file contents";
+        assert_eq!(
+            keyed_hash("integrity-key", data),
+            keyed_hash("integrity-key", data)
+        );
+        assert_ne!(
+            keyed_hash("integrity-key", data),
+            keyed_hash("other-key", data)
+        );
+    }
+
+    #[test]
+    fn verify_folder_flags_nothing_against_a_fresh_manifest() {
+        let folder = TempFolder::new("clean");
+        fs::write(folder.path.join("passwords.txt"), b"canary contents").unwrap();
+        let settings = test_settings();
+
+        write_manifest(folder.path_str(), &settings);
+        let flagged = verify_folder(folder.path_str(), &settings);
+
+        assert!(flagged.is_empty());
+    }
+
+    #[test]
+    fn verify_folder_flags_missing_and_changed_files() {
+        let folder = TempFolder::new("tampered");
+        fs::write(folder.path.join("passwords.txt"), b"canary contents").unwrap();
+        fs::write(folder.path.join("budget.txt"), b"other canary contents").unwrap();
+        let settings = test_settings();
+        write_manifest(folder.path_str(), &settings);
+
+        fs::remove_file(folder.path.join("passwords.txt")).unwrap();
+        fs::write(folder.path.join("budget.txt"), b"tampered").unwrap();
+
+        let mut flagged = verify_folder(folder.path_str(), &settings);
+        flagged.sort();
+
+        assert_eq!(flagged, vec!["budget.txt".to_string(), "passwords.txt".to_string()]);
+    }
+}
@@ -0,0 +1,413 @@
+//! # Notify Access Module
+//! This module defines the pluggable `AlertSink` backends a canary detection fans out to:
+//! the original webhook POST, a local syslog line, a Unix-domain-socket feed for a
+//! co-located collector, a local desktop notification to logged-in sessions, and a forced
+//! system shutdown.
+
+use crate::logger::{log_event, log_message, Severity};
+use crate::settings::{Settings, SinkConfig};
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A single detection event, handed to every configured [`AlertSink`].
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertEvent<'a> {
+    pub file: &'a str,
+}
+
+/// A backend that can be notified when a canary is disturbed.
+///
+/// Implementations should fail independently of one another: `modification_detection`
+/// logs a per-sink error and keeps going rather than aborting the whole fan-out.
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    async fn send(&self, event: &AlertEvent<'_>) -> Result<(), String>;
+}
+
+/// Name of the append-only queue file a failed webhook delivery is spooled to.
+const WEBHOOK_SPOOL_FILE: &str = "webhook_spool.jsonl";
+
+/// A webhook notification that exhausted its retries, persisted so it can be
+/// delivered on the next daemon startup.
+#[derive(Debug, Serialize, Deserialize)]
+struct SpooledNotification {
+    url: String,
+    file: String,
+}
+
+/// Sends the event as an HTTP POST, retrying with exponential backoff and jitter before
+/// spooling it to disk for at-least-once delivery across restarts (including the very
+/// reboot a detection triggers).
+pub struct WebhookSink {
+    url: String,
+    spool_dir: String,
+    retry_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    timeout: Duration,
+}
+
+impl WebhookSink {
+    pub fn new(url: String, settings: &Settings) -> Self {
+        WebhookSink {
+            url,
+            spool_dir: settings.spool_dir.clone(),
+            retry_attempts: settings.webhook_retry_attempts,
+            base_delay: Duration::from_millis(settings.webhook_retry_base_delay_ms),
+            max_delay: Duration::from_millis(settings.webhook_retry_max_delay_ms),
+            timeout: Duration::from_millis(settings.webhook_timeout_ms),
+        }
+    }
+
+    fn spool_path(&self) -> PathBuf {
+        Path::new(&self.spool_dir).join(WEBHOOK_SPOOL_FILE)
+    }
+
+    async fn post_once(&self, event: &AlertEvent<'_>) -> Result<(), String> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| format!("building client: {}", e))?;
+        let response = client
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await
+            .map_err(|e| format!("webhook request failed: {}", e))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("webhook returned status {}", response.status()))
+        }
+    }
+
+    /// Appends the notification to the spool file so it can be retried at startup.
+    fn spool(&self, event: &AlertEvent<'_>) {
+        let record = SpooledNotification {
+            url: self.url.clone(),
+            file: event.file.to_string(),
+        };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        let _ = fs::create_dir_all(&self.spool_dir);
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.spool_path())
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    async fn send(&self, event: &AlertEvent<'_>) -> Result<(), String> {
+        let mut delay = self.base_delay;
+        let mut last_err = "no attempts made".to_string();
+
+        for attempt in 1..=self.retry_attempts.max(1) {
+            match self.post_once(event).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    last_err = e;
+                    if attempt == self.retry_attempts.max(1) {
+                        break;
+                    }
+                    let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..=50));
+                    tokio::time::sleep(delay + jitter).await;
+                    delay = (delay * 2).min(self.max_delay);
+                }
+            }
+        }
+
+        self.spool(event);
+        Err(format!(
+            "all {} attempts failed ({}); spooled for retry",
+            self.retry_attempts.max(1), last_err
+        ))
+    }
+}
+
+/// Attempts to deliver every notification spooled under `settings.spool_dir`, dropping
+/// each one once it succeeds and leaving the rest queued. Called once at daemon startup,
+/// before monitoring begins.
+pub async fn drain_spool(settings: &Settings) {
+    let path = Path::new(&settings.spool_dir).join(WEBHOOK_SPOOL_FILE);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+
+    let mut remaining = Vec::new();
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<SpooledNotification>(line) {
+            Ok(record) => {
+                let sink = WebhookSink::new(record.url.clone(), settings);
+                let event = AlertEvent { file: &record.file };
+                match sink.post_once(&event).await {
+                    Ok(()) => {
+                        let msg = format!("Drained spooled notification for {}", record.file);
+                        log_message(&settings.log_file, &msg);
+                    }
+                    Err(_) => remaining.push(line.to_string()),
+                }
+            }
+            Err(_) => {
+                // Drop unparsable lines rather than spinning on them forever.
+            }
+        }
+    }
+
+    if remaining.is_empty() {
+        let _ = fs::remove_file(&path);
+    } else {
+        let _ = fs::write(&path, remaining.join("\n") + "\n");
+    }
+}
+
+/// Facility/severity used for the syslog line: `LOG_DAEMON` at `LOG_WARNING`.
+const SYSLOG_FACILITY_DAEMON: u8 = 3;
+const SYSLOG_SEVERITY_WARNING: u8 = 4;
+
+/// Writes a structured line to the local syslog daemon (`/dev/log`) under `LOG_DAEMON`,
+/// for hosts where an audit pipeline already collects the system log.
+pub struct SyslogSink;
+
+#[async_trait]
+impl AlertSink for SyslogSink {
+    async fn send(&self, event: &AlertEvent<'_>) -> Result<(), String> {
+        let priority = (SYSLOG_FACILITY_DAEMON * 8 + SYSLOG_SEVERITY_WARNING) as u32;
+        let payload = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        let line = format!("<{}>palangrotte: {}", priority, payload);
+
+        let socket = UnixDatagram::unbound().map_err(|e| format!("socket: {}", e))?;
+        socket
+            .connect("/dev/log")
+            .map_err(|e| format!("connect /dev/log: {}", e))?;
+        socket
+            .send(line.as_bytes())
+            .map_err(|e| format!("send: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Writes one JSON event per connection to a Unix-domain socket for a co-located
+/// collector to consume.
+pub struct UnixSocketSink {
+    path: String,
+}
+
+#[async_trait]
+impl AlertSink for UnixSocketSink {
+    async fn send(&self, event: &AlertEvent<'_>) -> Result<(), String> {
+        let payload = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        let mut stream = UnixStream::connect(&self.path)
+            .map_err(|e| format!("connect {}: {}", self.path, e))?;
+        stream
+            .write_all(payload.as_bytes())
+            .map_err(|e| format!("write: {}", e))?;
+        stream.write_all(b"\n").map_err(|e| format!("write: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Pops up `settings.notification_title`/`notification_message` on every logged-in
+/// desktop session: a `WTSSendMessageW` broadcast on Windows, or the embedded
+/// `notify-send` script on Unix.
+pub struct LocalNotificationSink {
+    title: String,
+    message: String,
+    log_file: String,
+}
+
+impl LocalNotificationSink {
+    pub fn new(settings: &Settings) -> Self {
+        LocalNotificationSink {
+            title: settings.notification_title.clone(),
+            message: settings.notification_message.clone(),
+            log_file: settings.log_file.clone(),
+        }
+    }
+}
+
+#[cfg(windows)]
+#[async_trait]
+impl AlertSink for LocalNotificationSink {
+    async fn send(&self, _event: &AlertEvent<'_>) -> Result<(), String> {
+        use std::ffi::OsStr;
+        use std::iter::once;
+        use std::os::windows::ffi::OsStrExt;
+        use std::ptr;
+        use windows_sys::Win32::System::RemoteDesktop::{
+            WTSActive, WTSEnumerateSessionsW, WTSFreeMemory, WTSSendMessageW,
+            WTS_CURRENT_SERVER_HANDLE, WTS_SESSION_INFOW,
+        };
+        use windows_sys::Win32::UI::WindowsAndMessaging::MB_OK;
+
+        let title: Vec<u16> = OsStr::new(&self.title).encode_wide().chain(once(0)).collect();
+        let message: Vec<u16> = OsStr::new(&self.message).encode_wide().chain(once(0)).collect();
+
+        let mut session_info_ptr: *mut WTS_SESSION_INFOW = ptr::null_mut();
+        let mut count = 0;
+
+        unsafe {
+            if WTSEnumerateSessionsW(
+                WTS_CURRENT_SERVER_HANDLE,
+                0,
+                1,
+                &mut session_info_ptr,
+                &mut count,
+            ) == 0
+            {
+                return Err("failed to enumerate user sessions".to_string());
+            }
+            let session_info = std::slice::from_raw_parts(session_info_ptr, count as usize);
+            for session in session_info {
+                if session.State == WTSActive {
+                    let mut response = 0;
+                    WTSSendMessageW(
+                        WTS_CURRENT_SERVER_HANDLE,
+                        session.SessionId,
+                        title.as_ptr() as *mut _,
+                        (title.len() - 1) as u32 * 2,
+                        message.as_ptr() as *mut _,
+                        (message.len() - 1) as u32 * 2,
+                        MB_OK,
+                        30, // timeout 30 seconds
+                        &mut response,
+                        1, // wait for response
+                    );
+                }
+            }
+            WTSFreeMemory(session_info_ptr as *mut _);
+        }
+        log_message(&self.log_file, "Successfully notified user sessions.");
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl AlertSink for LocalNotificationSink {
+    async fn send(&self, _event: &AlertEvent<'_>) -> Result<(), String> {
+        use crate::linux_notification::NOTIFY_SCRIPT;
+        use std::process::Command;
+
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(NOTIFY_SCRIPT)
+            .arg("notify-send-all") // This is $0 for the script
+            .arg(&self.title)
+            .arg(&self.message)
+            .status()
+            .map_err(|e| format!("error executing embedded notify script: {}", e))?;
+
+        if status.success() {
+            log_message(&self.log_file, "Successfully notified user sessions.");
+            Ok(())
+        } else {
+            Err(format!("notify script exited with {}", status))
+        }
+    }
+}
+
+/// Forces the machine to shut down, falling back to a graceful shutdown if the forced
+/// one fails. Ends the response chain for the rest of this detection's sinks, so it
+/// should usually be configured last in `alert_sinks`.
+pub struct ShutdownSink {
+    log_file: String,
+}
+
+impl ShutdownSink {
+    pub fn new(settings: &Settings) -> Self {
+        ShutdownSink {
+            log_file: settings.log_file.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for ShutdownSink {
+    async fn send(&self, _event: &AlertEvent<'_>) -> Result<(), String> {
+        log_event(
+            &self.log_file,
+            Severity::Critical,
+            Some("shutdown_attempted"),
+            "Attempting to force system shutdown...",
+        );
+        match system_shutdown::force_shutdown() {
+            Ok(_) => {
+                log_event(
+                    &self.log_file,
+                    Severity::Critical,
+                    Some("shutdown_forced"),
+                    "Forced system shutdown command executed successfully.",
+                );
+                Ok(())
+            }
+            Err(error) => {
+                let msg = format!(
+                    "Forced shutdown failed: {}. Attempting graceful shutdown...",
+                    error
+                );
+                log_event(&self.log_file, Severity::Critical, Some("shutdown_forced_failed"), &msg);
+                system_shutdown::shutdown()
+                    .map(|_| {
+                        log_event(
+                            &self.log_file,
+                            Severity::Critical,
+                            Some("shutdown_graceful"),
+                            "Graceful system shutdown command executed successfully.",
+                        );
+                    })
+                    .map_err(|error| format!("graceful shutdown also failed: {}", error))
+            }
+        }
+    }
+}
+
+/// Builds the configured sink list from `settings.alert_sinks`, once per detection.
+pub fn build_sinks(settings: &Settings) -> Vec<Box<dyn AlertSink>> {
+    settings
+        .alert_sinks
+        .iter()
+        .map(|config| -> Box<dyn AlertSink> {
+            match config {
+                SinkConfig::Webhook { url } => Box::new(WebhookSink::new(url.clone(), settings)),
+                SinkConfig::Syslog => Box::new(SyslogSink),
+                SinkConfig::UnixSocket { path } => Box::new(UnixSocketSink { path: path.clone() }),
+                SinkConfig::LocalNotification => Box::new(LocalNotificationSink::new(settings)),
+                SinkConfig::Shutdown => Box::new(ShutdownSink::new(settings)),
+            }
+        })
+        .collect()
+}
+
+/// Fans a detection event out to every configured sink, logging per-sink failures
+/// without letting one failing sink stop the others from being notified.
+///
+/// # Arguments
+///
+/// * `sinks` - The sinks built once via [`build_sinks`].
+/// * `file_name` - The name of the modified file or folder.
+/// * `log_file` - Where to log per-sink failures.
+pub async fn notify_sinks(sinks: &[Box<dyn AlertSink>], file_name: &str, log_file: &str) {
+    let event = AlertEvent { file: file_name };
+    for sink in sinks {
+        if let Err(e) = sink.send(&event).await {
+            let msg = format!("Alert sink failed for file {}: {}", file_name, e);
+            log_message(log_file, &msg);
+        }
+    }
+}
@@ -1,15 +1,79 @@
 //! # Settings Module
 //! This module defines the settings structure and provides a function to load settings from a TOML file.
 
+use crate::encryption::CipherAlgorithm;
+use crate::logger::LogFormat;
 use serde::Deserialize;
 use std::fs;
 
+/// A single configured alert backend; see `notify_access::build_sinks`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// HTTP POST to `url` (the original, and still default, notification path).
+    Webhook { url: String },
+    /// A structured line written to the local syslog daemon under `LOG_DAEMON`.
+    Syslog,
+    /// One JSON event per connection, written to a Unix-domain socket at `path`.
+    UnixSocket { path: String },
+    /// A desktop popup broadcast to every logged-in session (`notification_title`/
+    /// `notification_message`).
+    LocalNotification,
+    /// Forces the machine to shut down, falling back to a graceful shutdown on failure.
+    Shutdown,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub folders_file: String,
     pub log_file: String,
-    pub keyword: String,
+    /// Path of the key file written by `encrypter init`, holding the salt and
+    /// verification tag used to re-derive the passphrase subkeys at startup. Unlike
+    /// the old `keyword` field this is not itself a secret, so it's safe to keep in
+    /// `palangrotte.toml`.
+    pub key_file: String,
+    /// Which AEAD cipher newly encrypted files use; the header records the choice so a
+    /// change here never breaks decrypting an older file.
+    pub cipher: CipherAlgorithm,
+    /// URL the default `SinkConfig::Webhook` entry in `alert_sinks` is built with. Each
+    /// webhook sink posts to the `url` of its own `alert_sinks` entry, so changing this
+    /// field does not redirect a webhook entry listed in `palangrotte.toml`.
     pub service_url: String,
+    /// Ordered list of alert backends a detection is fanned out to.
+    pub alert_sinks: Vec<SinkConfig>,
+    /// Directory holding the append-only spool of webhook notifications that
+    /// exhausted their retries, pending delivery on the next startup.
+    pub spool_dir: String,
+    /// Number of webhook POST attempts before spooling the notification.
+    pub webhook_retry_attempts: u32,
+    /// Initial backoff delay (milliseconds) between webhook retry attempts, doubled
+    /// after each failure up to `webhook_retry_max_delay_ms`.
+    pub webhook_retry_base_delay_ms: u64,
+    /// Upper bound (milliseconds) on the exponential backoff delay.
+    pub webhook_retry_max_delay_ms: u64,
+    /// Per-request timeout (milliseconds) for a single webhook POST attempt.
+    pub webhook_timeout_ms: u64,
+    /// How often (in seconds) each canary folder's manifest is re-verified.
+    pub manifest_check_interval_secs: u64,
+    /// Path of the Unix-domain socket the control subsystem listens on for
+    /// `palangrotte-ctl` commands.
+    pub control_socket_path: String,
+    /// Log entry format: free-form text or one JSON object per line.
+    pub log_format: LogFormat,
+    /// Maximum size in bytes a log file may reach before it is rotated. `0` disables rotation.
+    pub max_log_size: u64,
+    /// Number of rotated log generations to keep on disk.
+    pub log_rotate_keep: u32,
+    /// How long (in milliseconds) to wait after the last touch to a given path before
+    /// weighing it against the trigger threshold.
+    pub debounce_window_ms: u64,
+    /// Weighted score that must be reached within `trigger_window_secs` before escalating
+    /// to the configured `alert_sinks`; each distinct disturbed file contributes its
+    /// `canary::event_weight(kind)` once, so deletions and renames count for more than a
+    /// metadata-only touch.
+    pub trigger_threshold: u32,
+    /// Rolling window (in seconds) over which `trigger_threshold` is evaluated.
+    pub trigger_window_secs: u64,
     pub canary_file_names: Vec<String>,
     pub canary_file_extensions: Vec<String>,
     pub min_canary_files: u32,
@@ -18,16 +82,47 @@ pub struct Settings {
     pub max_canary_file_size: u64,
     pub notification_title: String,
     pub notification_message: String,
+
+    /// Hex-encoded folders-file subkey, re-derived from the operator's passphrase at
+    /// startup (see `encryption::derive_key_material`). Never read from
+    /// `palangrotte.toml` and never written back to it.
+    #[serde(skip)]
+    pub canary_key: String,
+    /// Hex-encoded manifest/log-integrity subkey, derived alongside `canary_key`.
+    #[serde(skip)]
+    pub integrity_key: String,
 }
 
 impl Default for Settings {
     /// Creates a new `Settings` instance with default values.
     fn default() -> Self {
+        let service_url = "https://jerome.bousquie.fr/palangrotte/index.php".to_string();
         Settings {
             folders_file: "folders.enc".to_string(),
             log_file: "plgrt.log".to_string(),
-            keyword: "mustuflux".to_string(),
-            service_url: "https://jerome.bousquie.fr/palangrotte/index.php".to_string(),
+            key_file: "palangrotte.key".to_string(),
+            cipher: CipherAlgorithm::ChaCha20Poly1305,
+            alert_sinks: vec![
+                SinkConfig::Webhook {
+                    url: service_url.clone(),
+                },
+                SinkConfig::LocalNotification,
+                SinkConfig::Shutdown,
+            ],
+            service_url,
+            spool_dir: "spool".to_string(),
+            webhook_retry_attempts: 4,
+            webhook_retry_base_delay_ms: 250,
+            webhook_retry_max_delay_ms: 4000,
+            webhook_timeout_ms: 5000,
+            manifest_check_interval_secs: 60,
+            control_socket_path: "palangrotte.sock".to_string(),
+            log_format: LogFormat::Text,
+            max_log_size: 10 * 1024 * 1024,
+            log_rotate_keep: 5,
+            debounce_window_ms: 200,
+            trigger_threshold: 3,
+            trigger_window_secs: 5,
             canary_file_names: vec![
                 "passwords".to_string(),
                 "documentation".to_string(),
@@ -60,6 +155,8 @@ impl Default for Settings {
             notification_title: "Security Alert".to_string(),
             notification_message: "A canary file has been modified. The system is shutting down."
                 .to_string(),
+            canary_key: String::new(),
+            integrity_key: String::new(),
         }
     }
 }